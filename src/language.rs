@@ -0,0 +1,23 @@
+/// The languages for which this crate embeds a standard BIP39 wordlist.
+///
+/// BIP39 itself defines wordlists for ten languages, but word order encodes
+/// the mnemonic, so embedding anything short of the exact upstream list
+/// would silently produce mnemonics incompatible with every other BIP39
+/// implementation — worse than not offering the language at all. Only
+/// [`Language::English`] is vendored in; the other nine stay unimplemented
+/// until their official lists can be vendored too, so this enum only has the
+/// one variant rather than exposing names that compile but fail at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Language {
+    English,
+}
+
+impl Language {
+    /// Returns the embedded wordlist text for this language, one word per
+    /// line.
+    pub fn wordlist_text(self) -> &'static str {
+        match self {
+            Language::English => include_str!("wordlists/english.txt"),
+        }
+    }
+}
@@ -7,20 +7,25 @@ use std::io::prelude::*;
 use std::io::stdin;
 use std::process;
 
-fn init(wordlist_filepath: &str) -> Result<bip39::Bip39> {
-    let wordfile = fs::File::open(wordlist_filepath)?;
-    let mut words: Vec<String> = Vec::new();
-    for line in io::BufReader::new(wordfile).lines() {
-        words.push(line?.trim().to_string());
+/// Builds the wordlist, reading it from `BIP39_WORDLIST` if set and falling
+/// back to the embedded standard English wordlist otherwise.
+fn init() -> Result<bip39::Bip39> {
+    match env::var("BIP39_WORDLIST") {
+        Ok(wordlist_filepath) => {
+            let wordfile = fs::File::open(&wordlist_filepath)
+                .with_context(|| format!("reading file {}", wordlist_filepath))?;
+            let mut words: Vec<String> = Vec::new();
+            for line in io::BufReader::new(wordfile).lines() {
+                words.push(line?.trim().to_string());
+            }
+            bip39::Bip39::new(&words)
+        }
+        Err(_) => Ok(bip39::Bip39::english()),
     }
-    bip39::Bip39::new(&words)
 }
 
 fn run(command: &str) -> Result<(), Box<dyn Error>> {
-    let wordlist_filepath =
-        env::var("BIP39_WORDLIST").unwrap_or_else(|_| "/opt/bip39/wordlist.txt".to_string());
-    let bip39 =
-        init(&wordlist_filepath).with_context(|| format!("reading file {}", wordlist_filepath))?;
+    let bip39 = init()?;
     match command {
         "encode" => {
             let mut bytes: Vec<u8> = Vec::new();
@@ -39,24 +44,121 @@ fn run(command: &str) -> Result<(), Box<dyn Error>> {
             io::stdout().write_all(&decoded)?;
             Ok(())
         }
+        "seed" => {
+            let mut words = String::new();
+            stdin().read_to_string(&mut words)?;
+            let passphrase = passphrase_arg()?;
+            let seed = bip39.to_seed(words.trim(), &passphrase)?;
+            io::stdout().write_all(&seed)?;
+            Ok(())
+        }
+        "generate" => {
+            let strength = bip39::Strength::from_word_count(word_count_arg()?)?;
+            for word in bip39.generate(strength)? {
+                print!("{} ", word);
+            }
+            Ok(())
+        }
+        "verify" => match env::args().nth(2) {
+            Some(path) => verify_file(&bip39, &path),
+            None => verify_stdin(&bip39),
+        },
         _ => Err("Invalid command".into()),
     }
 }
 
+/// Verifies a single mnemonic read from stdin, printing `OK` or `FAILED`.
+///
+/// A checksum mismatch is a normal, expected outcome (the coreutils
+/// `--check` convention this models just reports it), not an exceptional
+/// one, so it exits non-zero directly rather than bubbling up an `Err` for
+/// `main` to wrap in a "Could not execute" line.
+fn verify_stdin(bip39: &bip39::Bip39) -> Result<(), Box<dyn Error>> {
+    let mut words = String::new();
+    stdin().read_to_string(&mut words)?;
+    if bip39.verify(words.trim())? {
+        println!("OK");
+        Ok(())
+    } else {
+        println!("FAILED");
+        process::exit(1);
+    }
+}
+
+/// Verifies every mnemonic in `path`, one per line (`mnemonic<TAB>label` or
+/// just the mnemonic), following the shasum/cksum `--check` convention:
+/// a per-line OK/FAILED report followed by matched/mismatched counts.
+fn verify_file(bip39: &bip39::Bip39, path: &str) -> Result<(), Box<dyn Error>> {
+    let file = fs::File::open(path).with_context(|| format!("reading file {}", path))?;
+    let mut matched = 0;
+    let mut mismatched = 0;
+    for line in io::BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (mnemonic, label) = match line.split_once('\t') {
+            Some((mnemonic, label)) => (mnemonic, label),
+            None => (line.as_str(), line.as_str()),
+        };
+        match bip39.verify(mnemonic) {
+            Ok(true) => {
+                println!("{}: OK", label);
+                matched += 1;
+            }
+            Ok(false) | Err(_) => {
+                println!("{}: FAILED", label);
+                mismatched += 1;
+            }
+        }
+    }
+    eprintln!("{} matched, {} mismatched", matched, mismatched);
+    if mismatched > 0 {
+        // One or more failures is a normal check-failed outcome (already
+        // reported above), not an exceptional one, so exit non-zero
+        // directly instead of bubbling up an `Err` for `main` to wrap.
+        process::exit(1);
+    }
+    Ok(())
+}
+
+/// Reads the requested mnemonic word count from the third process argument,
+/// e.g. `bip39 generate 24`.
+fn word_count_arg() -> Result<u32, Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+    let words = args.get(2).ok_or("generate requires a word count")?;
+    Ok(words.parse()?)
+}
+
+/// Reads the BIP39 passphrase from `--passphrase <value>` if present among
+/// the process arguments, falling back to the `BIP39_PASSPHRASE` env var,
+/// and defaulting to the empty passphrase.
+fn passphrase_arg() -> Result<String, Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--passphrase") {
+        let value = args
+            .get(index + 1)
+            .ok_or("--passphrase requires a value")?;
+        return Ok(value.clone());
+    }
+    Ok(env::var("BIP39_PASSPHRASE").unwrap_or_default())
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
     if args.len() < 2 {
         eprintln!(
-            "Missing required parameter.\n\nUsage: {} (decode|encode)",
+            "Missing required parameter.\n\nUsage: {} (decode|encode|seed|generate|verify)",
             args[0]
         );
         process::exit(1);
     }
     let command: &str = &args[1];
-    if command != "encode" && command != "decode" {
+    let known_commands = ["encode", "decode", "seed", "generate", "verify"];
+    if !known_commands.contains(&command) {
         eprintln!(
-            "Parameter must be 'encode' or 'decode'.\n\nUsage: {} (decode|encode)",
-            args[0]
+            "Parameter must be one of {:?}.\n\nUsage: {} (decode|encode|seed|generate|verify)",
+            known_commands, args[0]
         );
     }
     if let Err(err) = run(command) {